@@ -1,13 +1,62 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::{create_dir_all, File};
 use std::io::Write;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use clap::Parser;
+use chrono::{NaiveDate, TimeZone, Utc};
+use clap::{Parser, ValueEnum};
+use ego_tree::NodeId;
+use epub_builder::{EpubBuilder, EpubContent, ZipLibrary};
 use futures_util::future::join_all;
 use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+use url::Url;
+
+/// Maximum number of attempts `fetch_html` makes before giving up on a URL.
+const MAX_FETCH_ATTEMPTS: u32 = 3;
+/// Initial delay before the first retry; doubles on each subsequent attempt.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Content extraction strategy used on each fetched post.
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// The original h4/p/cite selector heuristic.
+    Selector,
+    /// Readability-style DOM scoring.
+    Readability,
+}
+
+/// Front matter format to prepend to each Markdown file.
+#[derive(Clone, Copy, ValueEnum)]
+enum FrontmatterFormat {
+    Toml,
+    Yaml,
+    None,
+}
+
+/// Output layout for archived posts.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// One Markdown file per post (the default).
+    Markdown,
+    /// A single EPUB collecting every post as a chapter.
+    Epub,
+}
+
+/// Feed format written alongside the archived posts.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FeedFormat {
+    Rss,
+    Atom,
+    None,
+}
 
 /// Command-line arguments structure
 #[derive(Parser)]
@@ -16,6 +65,33 @@ struct Args {
     base_url: String,
     /// Output directory to save the posts
     output_dir: String,
+    /// Only crawl links whose host matches this domain (e.g. `angaatopzoek.be`)
+    #[arg(long)]
+    allowed_domain: String,
+    /// Regex a URL must match to be treated as an archivable post
+    #[arg(long)]
+    url_pattern: String,
+    /// Content extraction strategy
+    #[arg(long, value_enum, default_value_t = Mode::Selector)]
+    mode: Mode,
+    /// Front matter format to prepend to each Markdown file
+    #[arg(long, value_enum, default_value_t = FrontmatterFormat::None)]
+    frontmatter: FrontmatterFormat,
+    /// Download inline images/assets and rewrite references to local relative paths
+    #[arg(long)]
+    download_assets: bool,
+    /// Output layout: one Markdown file per post, or a single bundled EPUB
+    #[arg(long, value_enum, default_value_t = OutputFormat::Markdown)]
+    format: OutputFormat,
+    /// Maximum number of posts fetched concurrently
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Feed format to generate alongside the archived posts
+    #[arg(long, value_enum, default_value_t = FeedFormat::None)]
+    feed: FeedFormat,
+    /// Bypass the incremental cache and re-fetch every post unconditionally
+    #[arg(long)]
+    force: bool,
 }
 
 #[tokio::main]
@@ -28,9 +104,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .user_agent("blog_archiver/1.0")
         .build()?;
 
-    // Fetch and parse the base URL
-    let base_html = fetch_html(&client, &args.base_url).await?;
-    let post_links = extract_post_links(&base_html)?;
+    let post_pattern = Regex::new(&args.url_pattern)?;
+
+    // Crawl the site starting from the base URL, following every in-scope
+    // link until the frontier drains, collecting archivable post URLs.
+    let post_links = crawl(&client, &args.base_url, &args.allowed_domain, &post_pattern).await?;
     println!("Found {} post links", post_links.len());
     println!("Post links: {:?}", post_links);
 
@@ -47,21 +125,102 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .progress_chars("#>-"),
     );
 
-    // Process posts with limited concurrency
+    // Bound how many posts are fetched at once so large blogs don't spawn
+    // thousands of simultaneous requests.
+    let semaphore = Arc::new(Semaphore::new(args.concurrency));
+
+    // A separate semaphore (same bound) for asset downloads: reusing the
+    // post semaphore would deadlock, since a post task already holds one of
+    // its permits while it fetches that post's assets.
+    let asset_semaphore = Arc::new(Semaphore::new(args.concurrency));
+
+    // Load the incremental-archiving cache so re-runs can skip posts whose
+    // content hasn't changed since the last archive.
+    let cache = Arc::new(Mutex::new(load_cache(&args.output_dir)));
+
+    // Fetch and (for Markdown output) write every post, with limited
+    // concurrency, collecting `(url, title, content)` for whichever
+    // post-processing steps run afterward (EPUB bundling, feed generation).
     let fetches = post_links.into_iter().map(|post_url| {
         let client = client.clone();
         let output_dir = args.output_dir.clone();
         let pb = pb.clone();
+        let mode = args.mode;
+        let frontmatter = args.frontmatter;
+        let download_assets = args.download_assets;
+        let format = args.format;
+        let force = args.force;
+        let semaphore = semaphore.clone();
+        let asset_semaphore = asset_semaphore.clone();
+        let cache = cache.clone();
         tokio::spawn(async move {
-            if let Err(e) = process_post(&client, &post_url, &output_dir).await {
-                eprintln!("Error processing {}: {}", post_url, e);
-            }
+            let _permit = semaphore.acquire_owned().await.unwrap();
+            let result = fetch_post(
+                &client,
+                &post_url,
+                &output_dir,
+                mode,
+                download_assets,
+                &asset_semaphore,
+                &cache,
+                force,
+            )
+            .await;
+            let parsed = match result {
+                Ok(Some(PostOutcome::Fetched { title, content })) => {
+                    if format == OutputFormat::Markdown {
+                        let filename = format_filename(&post_url);
+                        let filepath = Path::new(&output_dir).join(filename);
+                        let front_matter = build_front_matter(&post_url, &title, frontmatter);
+                        if let Err(e) =
+                            save_as_markdown(&filepath, &title, &content, front_matter.as_deref())
+                        {
+                            eprintln!("Error saving {}: {}", post_url, e);
+                        }
+                    }
+                    Some((post_url, title, content))
+                }
+                // Unchanged since last run: the Markdown file (if any) is
+                // already on disk, so only fold it into `posts` for
+                // EPUB/feed generation without rewriting it.
+                Ok(Some(PostOutcome::Cached { title, content })) => {
+                    Some((post_url, title, content))
+                }
+                Ok(None) => None,
+                Err(e) => {
+                    eprintln!("Error processing {}: {}", post_url, e);
+                    None
+                }
+            };
             pb.inc(1);
+            parsed
         })
     });
 
-    // Await all tasks
-    join_all(fetches).await;
+    let mut posts: Vec<(String, String, String)> = join_all(fetches)
+        .await
+        .into_iter()
+        .filter_map(|r| r.ok().flatten())
+        .collect();
+
+    save_cache(&args.output_dir, &cache.lock().unwrap())?;
+
+    posts.sort_by(|a, b| {
+        let date_a = parse_post_metadata(&a.0).map(|(date, _)| date);
+        let date_b = parse_post_metadata(&b.0).map(|(date, _)| date);
+        date_a.cmp(&date_b)
+    });
+
+    if args.format == OutputFormat::Epub {
+        let chapters: Vec<(String, String)> = posts
+            .iter()
+            .map(|(_, title, content)| (title.clone(), content.clone()))
+            .collect();
+        build_epub(&chapters, &args.output_dir)?;
+    }
+
+    let site_url = original_source_url(&args.base_url);
+    build_feed(&posts, &args.output_dir, args.feed, &site_url)?;
 
     // Finish the progress bar
     pb.finish_with_message("Processing complete");
@@ -69,45 +228,499 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Fetches the HTML content of a given URL
+/// Outcome of a conditional fetch: either the server confirmed the cached
+/// copy is still fresh (304), or a (possibly new) body came back along with
+/// whatever validators it carried.
+enum FetchOutcome {
+    NotModified,
+    Fetched {
+        body: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// Fetches the HTML content of a given URL, retrying with exponential
+/// backoff on transport errors and HTTP 429/5xx responses.
 async fn fetch_html(client: &reqwest::Client, url: &str) -> Result<String, reqwest::Error> {
-    let response = client.get(url).send().await?;
-    response.text().await
+    match fetch_html_conditional(client, url, None, None).await? {
+        FetchOutcome::Fetched { body, .. } => Ok(body),
+        FetchOutcome::NotModified => {
+            unreachable!("no validators were sent, so a 304 can't come back")
+        }
+    }
 }
 
-async fn process_post(client: &reqwest::Client, post_url: &str, output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let post_html = fetch_html(client, post_url).await?;
-    if let Some((title, content)) = extract_post_content(&post_html) {
-        let filename = format_filename(post_url);
-        let filepath = Path::new(output_dir).join(filename);
-        save_as_markdown(&filepath, &title, &content)?;
+/// Fetches a URL, optionally sending `If-None-Match`/`If-Modified-Since`
+/// validators so the server can reply `304 Not Modified`. Retries with
+/// exponential backoff on transport errors and HTTP 429/5xx responses.
+async fn fetch_html_conditional(
+    client: &reqwest::Client,
+    url: &str,
+    if_none_match: Option<&str>,
+    if_modified_since: Option<&str>,
+) -> Result<FetchOutcome, reqwest::Error> {
+    let mut delay = INITIAL_RETRY_DELAY;
+
+    for attempt in 1..=MAX_FETCH_ATTEMPTS {
+        let mut request = client.get(url);
+        if let Some(etag) = if_none_match {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = if_modified_since {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                if status == reqwest::StatusCode::NOT_MODIFIED {
+                    return Ok(FetchOutcome::NotModified);
+                }
+                if status.is_success() {
+                    let etag = header_str(&response, reqwest::header::ETAG);
+                    let last_modified = header_str(&response, reqwest::header::LAST_MODIFIED);
+                    let body = response.text().await?;
+                    return Ok(FetchOutcome::Fetched {
+                        body,
+                        etag,
+                        last_modified,
+                    });
+                }
+                if attempt == MAX_FETCH_ATTEMPTS || !is_retryable_status(status) {
+                    return Err(response.error_for_status().unwrap_err());
+                }
+            }
+            Err(e) => {
+                if attempt == MAX_FETCH_ATTEMPTS {
+                    return Err(e);
+                }
+            }
+        }
+
+        sleep(delay).await;
+        delay *= 2;
     }
-    Ok(())
+
+    unreachable!("loop always returns on its final attempt")
+}
+
+/// Reads a response header as an owned `String`, if present and valid UTF-8.
+fn header_str(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
 }
 
-/// Extracts post links from the base HTML by composing potential post URLs
-/// and verifying their presence in the base HTML.
-fn extract_post_links(html: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+/// Whether an HTTP status is worth retrying (rate limiting or a transient
+/// server error), as opposed to a permanent client error.
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Recursively crawls `start_url`, following every in-scope `<a href>` until
+/// the frontier drains, and returns every URL that matches `post_pattern`.
+///
+/// A page is only followed for further links if its host matches
+/// `allowed_domain`; it is additionally treated as an archivable post if its
+/// URL matches `post_pattern`.
+async fn crawl(
+    client: &reqwest::Client,
+    start_url: &str,
+    allowed_domain: &str,
+    post_pattern: &Regex,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut visited = HashSet::new();
+    let mut frontier = VecDeque::new();
+    let mut post_links = HashSet::new();
+
+    frontier.push_back(start_url.to_string());
+
+    while let Some(page_url) = frontier.pop_front() {
+        if !visited.insert(page_url.clone()) {
+            continue;
+        }
+
+        let html = match fetch_html(client, &page_url).await {
+            Ok(html) => html,
+            Err(e) => {
+                eprintln!("Error fetching {}: {}", page_url, e);
+                continue;
+            }
+        };
+
+        for link in extract_links(&html, &page_url) {
+            if !is_in_scope(&link, allowed_domain) {
+                continue;
+            }
+
+            if post_pattern.is_match(&link) {
+                post_links.insert(link.clone());
+            }
+
+            if !visited.contains(&link) {
+                frontier.push_back(link);
+            }
+        }
+    }
+
+    Ok(post_links.into_iter().collect())
+}
+
+/// Extracts every `<a href>` from `html`, normalized against `page_url`,
+/// dropping links with a fragment identifier.
+fn extract_links(html: &str, page_url: &str) -> Vec<String> {
     let document = Html::parse_document(html);
     let selector = Selector::parse("a").unwrap();
-    let mut unique_links = HashSet::new();
-
-    // Define a regex pattern to match URLs with a date and a title
-    let re = Regex::new(r"https://web\.archive\.org/web/\d+/http://angaatopzoek\.be/\d{4}/\d{2}/\d{2}/[^/]+/$")?;
+    let base = match Url::parse(page_url) {
+        Ok(base) => base,
+        Err(_) => return Vec::new(),
+    };
 
+    let mut links = Vec::new();
     for element in document.select(&selector) {
         if let Some(href) = element.value().attr("href") {
-            // Check if the href matches the desired pattern
-            if re.is_match(href) {
-                // Further filter out URLs containing a fragment identifier
-                if !href.contains('#') {
-                    unique_links.insert(href.to_string());
-                }
+            if href.contains('#') {
+                continue;
+            }
+            if let Ok(absolute) = base.join(href) {
+                links.push(absolute.to_string());
+            }
+        }
+    }
+
+    links
+}
+
+/// Checks whether `link` is within `allowed_domain`. Wayback Machine links
+/// all resolve to the host `web.archive.org`, so this matches against the
+/// host of the *original* URL embedded in the Wayback path, not the
+/// Wayback host itself.
+fn is_in_scope(link: &str, allowed_domain: &str) -> bool {
+    let original = original_source_url(link);
+    Url::parse(&original)
+        .map(|url| url.host_str() == Some(allowed_domain))
+        .unwrap_or(false)
+}
+
+/// A previously-archived post's validators and extracted content, used to
+/// skip re-fetching and re-writing content that hasn't changed while still
+/// making it available to EPUB/feed generation on incremental re-runs.
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_hash: String,
+    title: String,
+    content: String,
+}
+
+/// On-disk manifest mapping post URL to its cache entry, persisted as
+/// `.archive-cache.json` in `output_dir`.
+type Cache = HashMap<String, CacheEntry>;
+
+const CACHE_FILE_NAME: &str = ".archive-cache.json";
+
+/// Loads the incremental-archiving cache from `output_dir`, or an empty one
+/// if it doesn't exist yet or fails to parse.
+fn load_cache(output_dir: &str) -> Cache {
+    let path = Path::new(output_dir).join(CACHE_FILE_NAME);
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable cache at {}: {}", path.display(), e);
+            Cache::new()
+        }),
+        Err(_) => Cache::new(),
+    }
+}
+
+/// Writes the incremental-archiving cache back to `output_dir`.
+fn save_cache(output_dir: &str, cache: &Cache) -> std::io::Result<()> {
+    let path = Path::new(output_dir).join(CACHE_FILE_NAME);
+    let contents = serde_json::to_string_pretty(cache)?;
+    std::fs::write(path, contents)
+}
+
+/// Outcome of [`fetch_post`]: either freshly fetched and extracted content
+/// that still needs writing out, or content recovered unchanged from the
+/// cache that the caller should skip re-writing but still include in
+/// `posts` for EPUB/feed generation.
+enum PostOutcome {
+    Fetched { title: String, content: String },
+    Cached { title: String, content: String },
+}
+
+/// Fetches and extracts a post's `(title, content)`, optionally localizing
+/// its assets. Shared by both the per-file Markdown path and the EPUB path.
+///
+/// Consults `cache` first and issues a conditional request; when the server
+/// confirms the post is unchanged (a 304, or an identical content hash),
+/// returns the cached `title`/`content` as `PostOutcome::Cached` instead of
+/// re-extracting or re-writing anything. Pass `force: true` to always do a
+/// full, unconditional fetch.
+async fn fetch_post(
+    client: &reqwest::Client,
+    post_url: &str,
+    output_dir: &str,
+    mode: Mode,
+    download_assets: bool,
+    asset_semaphore: &Arc<Semaphore>,
+    cache: &Mutex<Cache>,
+    force: bool,
+) -> Result<Option<PostOutcome>, Box<dyn std::error::Error>> {
+    let cached_entry = if force {
+        None
+    } else {
+        cache.lock().unwrap().get(post_url).cloned()
+    };
+
+    let outcome = fetch_html_conditional(
+        client,
+        post_url,
+        cached_entry.as_ref().and_then(|e| e.etag.as_deref()),
+        cached_entry
+            .as_ref()
+            .and_then(|e| e.last_modified.as_deref()),
+    )
+    .await?;
+
+    let (post_html, etag, last_modified) = match outcome {
+        FetchOutcome::NotModified => {
+            return Ok(cached_entry.map(|cached| PostOutcome::Cached {
+                title: cached.title,
+                content: cached.content,
+            }));
+        }
+        FetchOutcome::Fetched {
+            body,
+            etag,
+            last_modified,
+        } => (body, etag, last_modified),
+    };
+
+    let content_hash = sha256_hex(post_html.as_bytes());
+    let unchanged = cached_entry
+        .as_ref()
+        .is_some_and(|cached| cached.content_hash == content_hash);
+
+    if unchanged {
+        let cached = cached_entry.unwrap();
+        cache.lock().unwrap().insert(
+            post_url.to_string(),
+            CacheEntry {
+                etag,
+                last_modified,
+                content_hash,
+                title: cached.title.clone(),
+                content: cached.content.clone(),
+            },
+        );
+        return Ok(Some(PostOutcome::Cached {
+            title: cached.title,
+            content: cached.content,
+        }));
+    }
+
+    let extracted = match mode {
+        Mode::Selector => extract_post_content(&post_html),
+        Mode::Readability => extract_post_content_readability(&post_html),
+    };
+
+    let Some((title, mut content)) = extracted else {
+        return Ok(None);
+    };
+
+    if download_assets {
+        let local_assets =
+            localize_assets(client, &post_html, post_url, output_dir, asset_semaphore).await?;
+        if !local_assets.is_empty() {
+            content.push_str("\n\n");
+            for (path, alt) in &local_assets {
+                content.push_str(&format!("![{}]({})\n", alt, path));
+            }
+        }
+    }
+
+    cache.lock().unwrap().insert(
+        post_url.to_string(),
+        CacheEntry {
+            etag,
+            last_modified,
+            content_hash,
+            title: title.clone(),
+            content: content.clone(),
+        },
+    );
+
+    Ok(Some(PostOutcome::Fetched { title, content }))
+}
+
+/// Hex-encoded SHA-256 digest of `bytes`, used as a content validator when a
+/// server doesn't provide an ETag or Last-Modified header.
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Extracts every `img`/`source` image URL referenced in `html`, normalized
+/// against `page_url` and deduplicated. `<a href>` targets are deliberately
+/// excluded — those are links to other pages, not inline assets.
+fn extract_asset_urls(html: &str, page_url: &str) -> Vec<(String, String)> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img[src], source[src]").unwrap();
+    let base = match Url::parse(page_url) {
+        Ok(base) => base,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut urls = HashMap::new();
+    for element in document.select(&selector) {
+        if let Some(src) = element.value().attr("src") {
+            if let Ok(absolute) = base.join(src) {
+                let alt = element.value().attr("alt").unwrap_or("").to_string();
+                urls.entry(absolute.to_string()).or_insert(alt);
             }
         }
     }
 
-    Ok(unique_links.into_iter().collect())
+    urls.into_iter().collect()
+}
+
+/// Derives a deduplicated, content-hash filename for an asset, keeping its
+/// original extension when one is present.
+fn content_hash_filename(url: &str, bytes: &[u8]) -> String {
+    let hash = sha256_hex(bytes);
+    let ext = Path::new(url)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("bin");
+    format!("{}.{}", &hash[..16], ext)
+}
+
+/// Downloads every image referenced by `post_html` into an `assets/`
+/// subdirectory under `output_dir`, using a content-hash filename so
+/// identical assets are only ever written once, and returns the relative
+/// local path and scraped alt text of each successfully downloaded image.
+///
+/// Acquires `semaphore` per asset so a post with many images doesn't fire
+/// them all at once — the same concurrency bound applied to posts.
+///
+/// Content extraction (`extract_post_content`/`extract_post_content_readability`)
+/// discards markup and keeps only text, so there's no `<img>` reference left
+/// in `content` to rewrite in place. As a deliberate compromise, the caller
+/// instead appends these as a trailing block of Markdown images rather than
+/// restoring them to their original position in the post.
+async fn localize_assets(
+    client: &reqwest::Client,
+    post_html: &str,
+    post_url: &str,
+    output_dir: &str,
+    semaphore: &Arc<Semaphore>,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let asset_urls = extract_asset_urls(post_html, post_url);
+    let assets_dir = Path::new(output_dir).join("assets");
+    create_dir_all(&assets_dir)?;
+
+    let fetches = asset_urls.into_iter().map(|(asset_url, alt)| {
+        let client = client.clone();
+        let assets_dir = assets_dir.clone();
+        let semaphore = semaphore.clone();
+        tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.unwrap();
+
+            let response = match client.get(&asset_url).send().await {
+                Ok(response) => response,
+                Err(e) => {
+                    eprintln!("Error downloading asset {}: {}", asset_url, e);
+                    return None;
+                }
+            };
+
+            let is_image = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|content_type| content_type.starts_with("image/"));
+            if !is_image {
+                return None;
+            }
+
+            let bytes = match response.bytes().await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!("Error downloading asset {}: {}", asset_url, e);
+                    return None;
+                }
+            };
+
+            let filename = content_hash_filename(&asset_url, &bytes);
+            let path = assets_dir.join(&filename);
+            if !path.exists() {
+                if let Err(e) = std::fs::write(&path, &bytes) {
+                    eprintln!("Error writing asset {}: {}", asset_url, e);
+                    return None;
+                }
+            }
+
+            Some((format!("assets/{}", filename), alt))
+        })
+    });
+
+    let results = join_all(fetches).await;
+    Ok(results
+        .into_iter()
+        .filter_map(|r| r.ok().flatten())
+        .collect())
+}
+
+/// Parses the `YYYY/MM/DD/slug` segment out of a post URL, as emitted by
+/// Wayback-captured blog paths.
+fn parse_post_metadata(post_url: &str) -> Option<(String, String)> {
+    let re = Regex::new(r"(\d{4})/(\d{2})/(\d{2})/([^/]+?)/?$").unwrap();
+    let caps = re.captures(post_url)?;
+    let date = format!("{}-{}-{}", &caps[1], &caps[2], &caps[3]);
+    let slug = caps[4].to_string();
+    Some((date, slug))
+}
+
+/// Strips the `https://web.archive.org/web/<timestamp>/` prefix off a
+/// Wayback Machine URL, recovering the original source URL.
+fn original_source_url(post_url: &str) -> String {
+    let re = Regex::new(r"^https?://web\.archive\.org/web/\d+[a-z_]*/").unwrap();
+    re.replace(post_url, "").to_string()
+}
+
+/// Escapes `\` and `"` so a value can be safely placed inside a
+/// double-quoted TOML or YAML scalar.
+fn escape_front_matter_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Builds a TOML/YAML front-matter block for `post_url`, or `None` when
+/// `format` is `FrontmatterFormat::None` or the URL carries no parseable date.
+fn build_front_matter(post_url: &str, title: &str, format: FrontmatterFormat) -> Option<String> {
+    let (date, slug) = parse_post_metadata(post_url)?;
+    let source_url = original_source_url(post_url);
+    let archived_at = Utc::now().to_rfc3339();
+
+    let title = escape_front_matter_string(title);
+    let slug = escape_front_matter_string(&slug);
+    let source_url = escape_front_matter_string(&source_url);
+
+    let block = match format {
+        FrontmatterFormat::None => return None,
+        FrontmatterFormat::Toml => format!(
+            "+++\ntitle = \"{title}\"\ndate = \"{date}\"\nslug = \"{slug}\"\nsource_url = \"{source_url}\"\narchived_at = \"{archived_at}\"\n+++\n\n",
+        ),
+        FrontmatterFormat::Yaml => format!(
+            "---\ntitle: \"{title}\"\ndate: {date}\nslug: \"{slug}\"\nsource_url: \"{source_url}\"\narchived_at: {archived_at}\n---\n\n",
+        ),
+    };
+
+    Some(block)
 }
 
 /// Extracts the content from a post's HTML
@@ -139,34 +752,283 @@ fn extract_post_content(html: &str) -> Option<(String, String)> {
     Some((title, content))
 }
 
-fn format_filename(url: &str) -> String {
-    // Extract the path after the domain
-    if let Some(path_start) = url.find("angaatopzoek.be") {
-        let path = &url[path_start + "angaatopzoek.be".len()..];
-        let sanitized_path = path
-            .trim_matches('/')
-            .replace('/', "_")
-            .replace('-', "_");
-
-        if sanitized_path.is_empty() {
-            "default_post.md".to_string()
-        } else {
-            format!("{}.md", sanitized_path)
+/// Negative class/id substrings that mark boilerplate (nav, ads, comments...).
+const NEGATIVE_PATTERNS: [&str; 6] = ["comment", "sidebar", "footer", "nav", "share", "promo"];
+/// Positive class/id substrings that mark likely article content.
+const POSITIVE_PATTERNS: [&str; 5] = ["article", "content", "post", "entry", "body"];
+
+/// Extracts post content with a Readability-style scoring pass: every
+/// block-level candidate is scored on its own text, a fraction of that score
+/// is propagated to its parent and grandparent, and the highest-scoring node
+/// overall is taken as the article root.
+fn extract_post_content_readability(html: &str) -> Option<(String, String)> {
+    let document = Html::parse_document(html);
+    let candidate_selector =
+        Selector::parse("p, div, article, section, td, pre, blockquote").unwrap();
+
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+    for element in document.select(&candidate_selector) {
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        let comma_count = text.matches(',').count() as f64;
+        let length_score = text.len() as f64 / 100.0;
+        let mut score = 1.0 + comma_count + length_score;
+        score *= class_id_weight(&element);
+
+        if score <= 0.0 {
+            continue;
         }
-    } else {
-        // Fallback in case the URL doesn't match the expected format
+
+        *scores.entry(element.id()).or_insert(0.0) += score;
+
+        if let Some(parent) = element.parent() {
+            *scores.entry(parent.id()).or_insert(0.0) += score * 0.5;
+            if let Some(grandparent) = parent.parent() {
+                *scores.entry(grandparent.id()).or_insert(0.0) += score * 0.25;
+            }
+        }
+    }
+
+    let (best_id, _) = scores
+        .into_iter()
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())?;
+    let best = ElementRef::wrap(document.tree.get(best_id)?)?;
+
+    // Prefer the body's own <h1>; `title, h4` would otherwise let the
+    // document's <head><title> (usually site-name boilerplate) win just
+    // because it comes first in document order.
+    let h1_selector = Selector::parse("h1").ok()?;
+    let h4_selector = Selector::parse("h4").ok()?;
+    let title_selector = Selector::parse("title").ok()?;
+
+    let title = document
+        .select(&h1_selector)
+        .next()
+        .or_else(|| document.select(&h4_selector).next())
+        .or_else(|| document.select(&title_selector).next())
+        .map(|node| node.text().collect::<Vec<_>>().join(" "))
+        .unwrap_or_else(|| "Untitled".to_string());
+
+    let content = best
+        .text()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Some((title, content))
+}
+
+/// Scores the `class`/`id` attributes of `element`: boilerplate-looking
+/// nodes are penalized, article-looking nodes are boosted.
+fn class_id_weight(element: &ElementRef) -> f64 {
+    let haystack = format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or(""),
+        element.value().attr("id").unwrap_or("")
+    )
+    .to_lowercase();
+
+    let mut weight = 1.0;
+    if NEGATIVE_PATTERNS.iter().any(|p| haystack.contains(p)) {
+        weight -= 0.7;
+    }
+    if POSITIVE_PATTERNS.iter().any(|p| haystack.contains(p)) {
+        weight += 0.5;
+    }
+
+    weight
+}
+
+/// Derives a Markdown filename for `url`, generic across source sites.
+///
+/// Prefers the `YYYY-MM-DD` date and slug that [`parse_post_metadata`]
+/// extracts; falls back to sanitizing the original URL's path when it
+/// doesn't match that `/yyyy/mm/dd/slug/` shape.
+fn format_filename(url: &str) -> String {
+    if let Some((date, slug)) = parse_post_metadata(url) {
+        return format!("{}-{}.md", date, slug);
+    }
+
+    let source_url = original_source_url(url);
+    let path = Url::parse(&source_url)
+        .map(|parsed| parsed.path().to_string())
+        .unwrap_or(source_url);
+    let sanitized_path = path.trim_matches('/').replace('/', "_");
+
+    if sanitized_path.is_empty() {
         "unknown_post.md".to_string()
+    } else {
+        format!("{}.md", sanitized_path)
     }
 }
 
-/// Saves the content as a Markdown file
-fn save_as_markdown(path: &Path, title: &str, content: &str) -> std::io::Result<()> {
+/// Writes `feed.xml` into `output_dir` summarizing every archived `post`
+/// (`url`, `title`, `content`), in the requested feed format. A no-op when
+/// `format` is `FeedFormat::None`. `site_url` is the original (non-archive)
+/// site the posts were crawled from, used for the feed-level `<link>`/`<id>`.
+fn build_feed(
+    posts: &[(String, String, String)],
+    output_dir: &str,
+    format: FeedFormat,
+    site_url: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let xml = match format {
+        FeedFormat::None => return Ok(()),
+        FeedFormat::Rss => render_rss_feed(posts, site_url),
+        FeedFormat::Atom => render_atom_feed(posts, site_url),
+    };
+
+    let path = Path::new(output_dir).join("feed.xml");
     let mut file = File::create(path)?;
+    write!(file, "{}", xml)?;
+
+    Ok(())
+}
+
+/// Converts a `YYYY-MM-DD` post date to an RFC-822 timestamp (midnight UTC)
+/// suitable for an RSS `<pubDate>`, falling back to the current time if the
+/// date can't be parsed.
+fn to_rfc822(date: &str) -> String {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| Utc.from_utc_datetime(&dt).to_rfc2822())
+        .unwrap_or_else(|| Utc::now().to_rfc2822())
+}
+
+/// Converts a `YYYY-MM-DD` post date to an RFC-3339 timestamp (midnight UTC)
+/// suitable for an Atom `<updated>`, falling back to the current time if the
+/// date can't be parsed.
+fn to_rfc3339(date: &str) -> String {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| Utc.from_utc_datetime(&dt).to_rfc3339())
+        .unwrap_or_else(|| Utc::now().to_rfc3339())
+}
+
+/// Renders an RSS 2.0 channel with one `<item>` per post.
+fn render_rss_feed(posts: &[(String, String, String)], site_url: &str) -> String {
+    let items: String = posts
+        .iter()
+        .map(|(url, title, content)| {
+            let date = parse_post_metadata(url).map(|(date, _)| date).unwrap_or_default();
+            let source_url = original_source_url(url);
+            format!(
+                "  <item>\n    <title>{}</title>\n    <link>{}</link>\n    <pubDate>{}</pubDate>\n    <description>{}</description>\n  </item>\n",
+                escape_xml(title),
+                escape_xml(&source_url),
+                escape_xml(&to_rfc822(&date)),
+                escape_xml(&excerpt(content, 280)),
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">\n<channel>\n  <title>Archived Blog</title>\n  <link>{}</link>\n  <description>Archive of blog posts</description>\n{}</channel>\n</rss>\n",
+        escape_xml(site_url),
+        items
+    )
+}
+
+/// Renders an Atom 1.0 feed with one `<entry>` per post.
+fn render_atom_feed(posts: &[(String, String, String)], site_url: &str) -> String {
+    let entries: String = posts
+        .iter()
+        .map(|(url, title, content)| {
+            let date = parse_post_metadata(url).map(|(date, _)| date).unwrap_or_default();
+            let source_url = original_source_url(url);
+            format!(
+                "  <entry>\n    <title>{}</title>\n    <id>{}</id>\n    <link href=\"{}\"/>\n    <updated>{}</updated>\n    <summary>{}</summary>\n  </entry>\n",
+                escape_xml(title),
+                escape_xml(&source_url),
+                escape_xml(&source_url),
+                escape_xml(&to_rfc3339(&date)),
+                escape_xml(&excerpt(content, 280)),
+            )
+        })
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <title>Archived Blog</title>\n  <id>{}</id>\n  <updated>{}</updated>\n  <author>\n    <name>Archived Blog</name>\n  </author>\n{}</feed>\n",
+        escape_xml(site_url),
+        escape_xml(&Utc::now().to_rfc3339()),
+        entries
+    )
+}
+
+/// Truncates `text` to at most `max_chars` characters, appending `...` when
+/// it was cut short.
+fn excerpt(text: &str, max_chars: usize) -> String {
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    if text.chars().count() > max_chars {
+        truncated.push_str("...");
+    }
+    truncated
+}
+
+/// Escapes characters that are not valid inside XML text content.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Bundles `chapters` (in order) into a single `archive.epub` under
+/// `output_dir`, one chapter per post with its title as an `<h1>` and its
+/// paragraphs as `<p>` elements.
+fn build_epub(
+    chapters: &[(String, String)],
+    output_dir: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut epub = EpubBuilder::new(ZipLibrary::new()?)?;
+
+    for (index, (title, content)) in chapters.iter().enumerate() {
+        let paragraphs = content
+            .split("\n\n")
+            .map(|p| format!("<p>{}</p>", escape_xml(p)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let chapter_html = format!("<h1>{}</h1>\n{}", escape_xml(title), paragraphs);
+
+        epub.add_content(
+            EpubContent::new(format!("chapter_{}.xhtml", index), chapter_html.as_bytes())
+                .title(title),
+        )?;
+    }
+
+    let path = Path::new(output_dir).join("archive.epub");
+    let mut file = File::create(path)?;
+    epub.generate(&mut file)?;
+
+    Ok(())
+}
+
+/// Saves the content as a Markdown file, optionally preceded by a
+/// front-matter block.
+fn save_as_markdown(
+    path: &Path,
+    title: &str,
+    content: &str,
+    front_matter: Option<&str>,
+) -> std::io::Result<()> {
+    let mut file = File::create(path)?;
+
+    // Write the front matter, if any
+    if let Some(front_matter) = front_matter {
+        write!(file, "{}", front_matter)?;
+    }
 
     // Write the title
     if !title.is_empty() {
-        writeln!(file, "# {}
-", title)?;
+        writeln!(
+            file,
+            "# {}
+",
+            title
+        )?;
     }
 
     // Write the content